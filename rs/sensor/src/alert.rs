@@ -0,0 +1,232 @@
+//! Threshold-based local alerting with hysteresis.
+//!
+//! Mirrors the programmable interrupt-threshold wake-ups some humidity and
+//! temperature sensors expose in hardware, implemented in software so it
+//! also works with parts (like the AHT20) that lack them. Per-metric arm
+//! state is tracked across loop iterations: once a threshold trips, the
+//! metric only re-arms after the value returns past `threshold ± margin`,
+//! so a noisy sample sitting right at the limit doesn't flap.
+
+use crate::sensor::Measurement;
+use reqwest::blocking::Client;
+
+/// Which side of which metric a threshold watches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlertKind {
+    HighTemperature,
+    LowTemperature,
+    HighHumidity,
+    LowHumidity,
+}
+
+enum Direction {
+    Above,
+    Below,
+}
+
+impl AlertKind {
+    fn value(self, measurement: &Measurement) -> f32 {
+        match self {
+            AlertKind::HighTemperature | AlertKind::LowTemperature => measurement.temperature_c,
+            AlertKind::HighHumidity | AlertKind::LowHumidity => measurement.humidity_pct,
+        }
+    }
+
+    fn direction(self) -> Direction {
+        match self {
+            AlertKind::HighTemperature | AlertKind::HighHumidity => Direction::Above,
+            AlertKind::LowTemperature | AlertKind::LowHumidity => Direction::Below,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            AlertKind::HighTemperature => "high temperature",
+            AlertKind::LowTemperature => "low temperature",
+            AlertKind::HighHumidity => "high humidity",
+            AlertKind::LowHumidity => "low humidity",
+        }
+    }
+}
+
+/// A limit plus the deadband a value must cross back over before the
+/// threshold re-arms.
+#[derive(Debug, Clone, Copy)]
+pub struct Threshold {
+    pub limit: f32,
+    pub margin: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArmState {
+    Armed,
+    Tripped,
+}
+
+struct Metric {
+    kind: AlertKind,
+    threshold: Threshold,
+    state: ArmState,
+}
+
+/// Which thresholds are configured, and where to deliver a fired alert
+/// besides the log line.
+pub struct AlertConfig {
+    pub high_temperature: Option<Threshold>,
+    pub low_temperature: Option<Threshold>,
+    pub high_humidity: Option<Threshold>,
+    pub low_humidity: Option<Threshold>,
+    pub webhook_url: Option<String>,
+}
+
+/// Evaluates configured thresholds against each reading and fires alerts
+/// with hysteresis, so the device can notice excursions without depending
+/// on the cloud pipeline or hardware interrupt support.
+pub struct Alerts {
+    client: Client,
+    webhook_url: Option<String>,
+    metrics: Vec<Metric>,
+}
+
+impl Alerts {
+    pub fn new(config: AlertConfig) -> Self {
+        let mut metrics = Vec::new();
+        let mut push = |kind: AlertKind, threshold: Option<Threshold>| {
+            if let Some(threshold) = threshold {
+                metrics.push(Metric {
+                    kind,
+                    threshold,
+                    state: ArmState::Armed,
+                });
+            }
+        };
+        push(AlertKind::HighTemperature, config.high_temperature);
+        push(AlertKind::LowTemperature, config.low_temperature);
+        push(AlertKind::HighHumidity, config.high_humidity);
+        push(AlertKind::LowHumidity, config.low_humidity);
+
+        Self {
+            client: Client::new(),
+            webhook_url: config.webhook_url,
+            metrics,
+        }
+    }
+
+    /// Checks every configured threshold against `measurement`, firing (and
+    /// re-arming) alerts as needed.
+    pub fn check(&mut self, location: &str, device: &str, measurement: &Measurement) {
+        for metric in &mut self.metrics {
+            let value = metric.kind.value(measurement);
+            let Threshold { limit, margin } = metric.threshold;
+
+            let (tripped, rearmed) = match metric.kind.direction() {
+                Direction::Above => (value >= limit, value <= limit - margin),
+                Direction::Below => (value <= limit, value >= limit + margin),
+            };
+
+            match metric.state {
+                ArmState::Armed if tripped => {
+                    metric.state = ArmState::Tripped;
+                    fire(
+                        &self.client,
+                        self.webhook_url.as_deref(),
+                        location,
+                        device,
+                        metric.kind,
+                        value,
+                        limit,
+                    );
+                }
+                ArmState::Tripped if rearmed => {
+                    metric.state = ArmState::Armed;
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+fn fire(
+    client: &Client,
+    webhook_url: Option<&str>,
+    location: &str,
+    device: &str,
+    kind: AlertKind,
+    value: f32,
+    limit: f32,
+) {
+    println!(
+        "ALERT [{location}/{device}] {label} crossed threshold: value={value:.2} limit={limit:.2}",
+        label = kind.label(),
+    );
+
+    let Some(url) = webhook_url else {
+        return;
+    };
+
+    let body = serde_json::json!({
+        "location": location,
+        "device": device,
+        "alert": kind.label(),
+        "value": value,
+        "limit": limit,
+    });
+
+    if let Err(e) = client.post(url).json(&body).send() {
+        eprintln!("failed to deliver alert webhook: {e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reading(temperature_c: f32) -> Measurement {
+        Measurement {
+            temperature_c,
+            humidity_pct: 50.0,
+            pressure_pa: None,
+        }
+    }
+
+    fn high_temperature_alerts(limit: f32, margin: f32) -> Alerts {
+        Alerts::new(AlertConfig {
+            high_temperature: Some(Threshold { limit, margin }),
+            low_temperature: None,
+            high_humidity: None,
+            low_humidity: None,
+            webhook_url: None,
+        })
+    }
+
+    #[test]
+    fn trips_once_value_reaches_limit() {
+        let mut alerts = high_temperature_alerts(30.0, 2.0);
+        alerts.check("loc", "dev", &reading(29.0));
+        assert_eq!(alerts.metrics[0].state, ArmState::Armed);
+
+        alerts.check("loc", "dev", &reading(30.0));
+        assert_eq!(alerts.metrics[0].state, ArmState::Tripped);
+    }
+
+    #[test]
+    fn stays_tripped_within_the_margin() {
+        let mut alerts = high_temperature_alerts(30.0, 2.0);
+        alerts.check("loc", "dev", &reading(31.0));
+        assert_eq!(alerts.metrics[0].state, ArmState::Tripped);
+
+        // Still above limit - margin (28.0), so it shouldn't re-arm yet.
+        alerts.check("loc", "dev", &reading(29.0));
+        assert_eq!(alerts.metrics[0].state, ArmState::Tripped);
+    }
+
+    #[test]
+    fn rearms_once_value_clears_the_margin() {
+        let mut alerts = high_temperature_alerts(30.0, 2.0);
+        alerts.check("loc", "dev", &reading(31.0));
+        assert_eq!(alerts.metrics[0].state, ArmState::Tripped);
+
+        alerts.check("loc", "dev", &reading(28.0));
+        assert_eq!(alerts.metrics[0].state, ArmState::Armed);
+    }
+}
@@ -0,0 +1,290 @@
+//! Layered runtime configuration.
+//!
+//! `sensor_id`, `endpoint`, `location`, the auth token, and the sample
+//! interval used to be compile-time constants, so deploying this binary to
+//! a new room or building meant a rebuild. Config is now resolved in
+//! layers, each overriding the last: built-in defaults, an optional TOML
+//! file (`--config`), `THERMONITOR_*` environment variables, and finally
+//! CLI flags — then validated once at startup instead of failing deep in
+//! the reading loop.
+
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+/// The reading loop's delay call takes a `u16` count of milliseconds, so
+/// that's the largest sample interval the device can actually honor.
+const MAX_SAMPLE_INTERVAL_MS: u64 = u16::MAX as u64;
+
+/// Where the config file layer is read from when `--config` isn't given.
+/// Unlike an explicitly-named path, its absence isn't an error: most
+/// deployments are expected to rely on env vars and CLI flags alone.
+const DEFAULT_CONFIG_PATH: &str = "/etc/thermonitor/config.toml";
+
+/// Fully resolved, validated configuration the rest of the program runs on.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub endpoint: String,
+    pub location: String,
+    pub device_id: String,
+    pub auth_token: String,
+    pub sample_interval: Duration,
+    pub i2c_path: String,
+}
+
+/// Mirrors [`Config`], but every field optional, so each layer only needs
+/// to specify what it overrides.
+#[derive(Debug, Default, Deserialize)]
+pub struct ConfigLayer {
+    pub endpoint: Option<String>,
+    pub location: Option<String>,
+    pub device_id: Option<String>,
+    pub auth_token: Option<String>,
+    pub sample_interval_ms: Option<u64>,
+    pub i2c_path: Option<String>,
+}
+
+impl ConfigLayer {
+    fn merge(self, over: ConfigLayer) -> ConfigLayer {
+        ConfigLayer {
+            endpoint: over.endpoint.or(self.endpoint),
+            location: over.location.or(self.location),
+            device_id: over.device_id.or(self.device_id),
+            auth_token: over.auth_token.or(self.auth_token),
+            sample_interval_ms: over.sample_interval_ms.or(self.sample_interval_ms),
+            i2c_path: over.i2c_path.or(self.i2c_path),
+        }
+    }
+
+    fn from_file(path: &Path) -> Result<ConfigLayer, String> {
+        let contents =
+            fs::read_to_string(path).map_err(|e| format!("reading {}: {e}", path.display()))?;
+        toml::from_str(&contents).map_err(|e| format!("parsing {}: {e}", path.display()))
+    }
+
+    fn from_env() -> ConfigLayer {
+        let var = |name: &str| std::env::var(name).ok();
+        ConfigLayer {
+            endpoint: var("THERMONITOR_ENDPOINT"),
+            location: var("THERMONITOR_LOCATION"),
+            device_id: var("THERMONITOR_DEVICE_ID"),
+            auth_token: var("THERMONITOR_AUTH_TOKEN"),
+            sample_interval_ms: var("THERMONITOR_SAMPLE_INTERVAL_MS").and_then(|v| v.parse().ok()),
+            i2c_path: var("THERMONITOR_I2C_PATH"),
+        }
+    }
+
+    fn defaults() -> ConfigLayer {
+        ConfigLayer {
+            endpoint: Some(
+                "https://bko7deq544.execute-api.us-east-2.amazonaws.com/dev/sensors".into(),
+            ),
+            location: Some("45203".into()),
+            device_id: Some("sensor".into()),
+            // No default: a baked-in auth token would defeat the point of
+            // moving secrets out of the source. Unset is a validation error.
+            auth_token: None,
+            sample_interval_ms: Some(5000),
+            i2c_path: None,
+        }
+    }
+}
+
+/// Builds the final [`Config`] by layering defaults < config file < env
+/// vars < CLI overrides, then validates the result.
+///
+/// `config_path` is the file named by `--config`, if any. When given, it
+/// must exist — a typo'd path is a startup error, not a silently-skipped
+/// layer. When not given, [`DEFAULT_CONFIG_PATH`] is tried instead, but its
+/// absence is expected and not an error.
+pub fn load(config_path: Option<&Path>, cli: ConfigLayer) -> Result<Config, String> {
+    let mut layer = ConfigLayer::defaults();
+
+    match config_path {
+        Some(path) => {
+            if !path.exists() {
+                return Err(format!("config file {} does not exist", path.display()));
+            }
+            layer = layer.merge(ConfigLayer::from_file(path)?);
+        }
+        None => {
+            let default_path = Path::new(DEFAULT_CONFIG_PATH);
+            if default_path.exists() {
+                layer = layer.merge(ConfigLayer::from_file(default_path)?);
+            }
+        }
+    }
+
+    layer = layer.merge(ConfigLayer::from_env());
+    layer = layer.merge(cli);
+
+    let endpoint = layer
+        .endpoint
+        .ok_or("endpoint is required (config file, THERMONITOR_ENDPOINT, or --endpoint)")?;
+    let location = layer
+        .location
+        .ok_or("location is required (config file, THERMONITOR_LOCATION, or --location)")?;
+    let device_id = layer
+        .device_id
+        .ok_or("device id is required (config file, THERMONITOR_DEVICE_ID, or --device-id)")?;
+    let auth_token = layer
+        .auth_token
+        .ok_or("auth token is required (config file, THERMONITOR_AUTH_TOKEN, or --auth-token)")?;
+    let sample_interval_ms = layer
+        .sample_interval_ms
+        .ok_or("sample interval is required")?;
+    let i2c_path = layer
+        .i2c_path
+        .ok_or("i2c bus path is required (positional argument or --i2c-path)")?;
+
+    if !endpoint.starts_with("http://") && !endpoint.starts_with("https://") {
+        return Err(format!("endpoint must be an http(s) URL, got {endpoint:?}"));
+    }
+    if sample_interval_ms == 0 {
+        return Err("sample interval must be greater than 0ms".into());
+    }
+    if sample_interval_ms > MAX_SAMPLE_INTERVAL_MS {
+        return Err(format!(
+            "sample interval must be at most {MAX_SAMPLE_INTERVAL_MS}ms, got {sample_interval_ms}ms"
+        ));
+    }
+
+    Ok(Config {
+        endpoint,
+        location,
+        device_id,
+        auth_token,
+        sample_interval: Duration::from_millis(sample_interval_ms),
+        i2c_path,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// `load` always merges in the real process environment, so any test
+    /// touching `THERMONITOR_*` vars would race with every other test in
+    /// this module if they ran in parallel (the default). Every test takes
+    /// this lock first to serialize them.
+    static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+    fn cli(i2c_path: &str) -> ConfigLayer {
+        ConfigLayer {
+            i2c_path: Some(i2c_path.into()),
+            ..Default::default()
+        }
+    }
+
+    fn write_temp_toml(name: &str, contents: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(name);
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn defaults_fill_in_when_nothing_else_is_set() {
+        let _guard = ENV_LOCK.lock().unwrap();
+        // The auth token has no default, so it must always come from a layer.
+        let mut layer = cli("/dev/i2c-1");
+        layer.auth_token = Some("token".into());
+        let cfg = load(None, layer).unwrap();
+        assert_eq!(cfg.location, "45203");
+        assert_eq!(cfg.device_id, "sensor");
+        assert_eq!(cfg.sample_interval, Duration::from_millis(5000));
+    }
+
+    #[test]
+    fn cli_overrides_defaults() {
+        let _guard = ENV_LOCK.lock().unwrap();
+        let mut layer = cli("/dev/i2c-1");
+        layer.auth_token = Some("token".into());
+        layer.location = Some("overridden".into());
+        let cfg = load(None, layer).unwrap();
+        assert_eq!(cfg.location, "overridden");
+    }
+
+    #[test]
+    fn missing_auth_token_is_a_validation_error() {
+        let _guard = ENV_LOCK.lock().unwrap();
+        let err = load(None, cli("/dev/i2c-1")).unwrap_err();
+        assert!(err.contains("auth token"), "got {err:?}");
+    }
+
+    #[test]
+    fn rejects_sample_interval_above_u16_millis() {
+        let _guard = ENV_LOCK.lock().unwrap();
+        let mut layer = cli("/dev/i2c-1");
+        layer.auth_token = Some("token".into());
+        layer.sample_interval_ms = Some(MAX_SAMPLE_INTERVAL_MS + 1);
+        let err = load(None, layer).unwrap_err();
+        assert!(err.contains("sample interval"), "got {err:?}");
+    }
+
+    #[test]
+    fn rejects_zero_sample_interval() {
+        let _guard = ENV_LOCK.lock().unwrap();
+        let mut layer = cli("/dev/i2c-1");
+        layer.auth_token = Some("token".into());
+        layer.sample_interval_ms = Some(0);
+        let err = load(None, layer).unwrap_err();
+        assert!(err.contains("sample interval"), "got {err:?}");
+    }
+
+    #[test]
+    fn config_file_overrides_defaults_but_loses_to_cli() {
+        let _guard = ENV_LOCK.lock().unwrap();
+        std::env::remove_var("THERMONITOR_LOCATION");
+
+        let path = write_temp_toml(
+            "thermonitor_test_file_overrides_defaults.toml",
+            "location = \"from-file\"\ndevice_id = \"from-file-device\"\n",
+        );
+        let mut layer = cli("/dev/i2c-1");
+        layer.auth_token = Some("token".into());
+
+        let cfg = load(Some(&path), layer.clone()).unwrap();
+        assert_eq!(cfg.location, "from-file");
+        assert_eq!(cfg.device_id, "from-file-device");
+
+        layer.location = Some("from-cli".into());
+        let cfg = load(Some(&path), layer).unwrap();
+        assert_eq!(cfg.location, "from-cli", "cli must win over the config file");
+
+        fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn env_var_overrides_file_but_loses_to_cli() {
+        let _guard = ENV_LOCK.lock().unwrap();
+        let path = write_temp_toml(
+            "thermonitor_test_env_overrides_file.toml",
+            "location = \"from-file\"\n",
+        );
+        std::env::set_var("THERMONITOR_LOCATION", "from-env");
+
+        let mut layer = cli("/dev/i2c-1");
+        layer.auth_token = Some("token".into());
+        let cfg = load(Some(&path), layer.clone()).unwrap();
+        assert_eq!(cfg.location, "from-env", "env must win over the config file");
+
+        layer.location = Some("from-cli".into());
+        let cfg = load(Some(&path), layer).unwrap();
+        assert_eq!(cfg.location, "from-cli", "cli must win over env");
+
+        std::env::remove_var("THERMONITOR_LOCATION");
+        fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn explicit_missing_config_file_is_an_error() {
+        let _guard = ENV_LOCK.lock().unwrap();
+        let path = std::env::temp_dir().join("thermonitor_test_missing_config.toml");
+        fs::remove_file(&path).ok();
+
+        let err = load(Some(&path), cli("/dev/i2c-1")).unwrap_err();
+        assert!(err.contains("does not exist"), "got {err:?}");
+    }
+}
@@ -1,91 +1,516 @@
 //! # Sensor
-//! 
+//!
 //! A simple program to access an I2C temperature/humidity sensor
 //! and push the data to an AWS DynamoDB table.
 
+mod alert;
+mod config;
+mod influx;
+mod queue;
+mod sensor;
+mod transport;
+
 use {
-    aht20::*,
+    alert::{AlertConfig, Alerts, Threshold},
     chrono::Utc,
+    config::ConfigLayer,
     embedded_hal::blocking::delay::DelayMs,
+    influx::{InfluxConfig, InfluxSink, Sink},
     linux_embedded_hal as hal,
-    reqwest::blocking::Client,
-    serde::ser::{Serialize, Serializer, SerializeStruct},
-    std::{env, process},
+    queue::{ResilientConfig, ResilientSender},
+    sensor::SensorKind,
+    std::{env, path::Path, process, time::Duration},
+    transport::{HttpTransport, MqttConfig, MqttTransport, PostData, Transport, TransportKind},
 };
 
-struct PostData {
-    location: String,
-    device: String,
-    timestamp: i64,
-    temperature: f32,
-    humidity: f32,
+const DEFAULT_ALERT_MARGIN: f32 = 0.5;
+const DEFAULT_QUEUE_CAPACITY: usize = 1000;
+const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Which sink (none, or a local time-series store) receives readings in
+/// addition to the cloud transport, selected via `--sink`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SinkKind {
+    None,
+    Influx,
 }
 
-impl Serialize for PostData {
-    /// Defines how a PostData object is serialized for transmission
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: Serializer,
-    {
-        let mut rgb = serializer.serialize_struct("PostData", 5)?;
-        rgb.serialize_field("LocationId", &self.location)?;
-        rgb.serialize_field("DeviceId", &self.device)?;
-        rgb.serialize_field("EpochTime", &self.timestamp)?;
-        rgb.serialize_field("Temperature", &self.temperature)?;
-        rgb.serialize_field("Humidity", &self.humidity)?;
-        rgb.end()
+impl SinkKind {
+    fn from_arg(s: &str) -> Option<Self> {
+        match s {
+            "none" => Some(SinkKind::None),
+            "influx" => Some(SinkKind::Influx),
+            _ => None,
+        }
     }
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let sensor_id = "sensor";
-    let endpoint = "https://bko7deq544.execute-api.us-east-2.amazonaws.com/dev/sensors";
-    let location = "45203";
+/// Parsed command-line invocation.
+struct Args {
+    config_path: Option<String>,
+    i2c_path: Option<String>,
+    endpoint: Option<String>,
+    location: Option<String>,
+    device_id: Option<String>,
+    auth_token: Option<String>,
+    sample_interval_ms: Option<u64>,
+    sensor_kind: SensorKind,
+    transport_kind: TransportKind,
+    mqtt_broker: Option<String>,
+    mqtt_topic: Option<String>,
+    ca_cert_path: Option<String>,
+    device_cert_path: Option<String>,
+    private_key_path: Option<String>,
+    sink_kind: SinkKind,
+    influx_url: Option<String>,
+    influx_db: Option<String>,
+    influx_user: Option<String>,
+    influx_pass: Option<String>,
+    influx_batch_size: usize,
+    temp_high: Option<f32>,
+    temp_high_margin: f32,
+    temp_low: Option<f32>,
+    temp_low_margin: f32,
+    humidity_high: Option<f32>,
+    humidity_high_margin: f32,
+    humidity_low: Option<f32>,
+    humidity_low_margin: f32,
+    alert_webhook: Option<String>,
+    queue_capacity: usize,
+    queue_path: Option<String>,
+}
 
-    let args: Vec<String> = env::args().collect();
-    if args.len() != 2 {
-        println!("usage: {} /dev/i2c-N", args[0]);
-        process::exit(1);
+fn parse_args() -> Args {
+    let argv: Vec<String> = env::args().collect();
+    let mut config_path = None;
+    let mut i2c_path = None;
+    let mut endpoint = None;
+    let mut location = None;
+    let mut device_id = None;
+    let mut auth_token = None;
+    let mut sample_interval_ms = None;
+    let mut sensor_kind = SensorKind::Aht20;
+    let mut transport_kind = TransportKind::Http;
+    let mut mqtt_broker = None;
+    let mut mqtt_topic = None;
+    let mut ca_cert_path = None;
+    let mut device_cert_path = None;
+    let mut private_key_path = None;
+    let mut sink_kind = SinkKind::None;
+    let mut influx_url = None;
+    let mut influx_db = None;
+    let mut influx_user = None;
+    let mut influx_pass = None;
+    let mut influx_batch_size = 10;
+    let mut temp_high = None;
+    let mut temp_high_margin = DEFAULT_ALERT_MARGIN;
+    let mut temp_low = None;
+    let mut temp_low_margin = DEFAULT_ALERT_MARGIN;
+    let mut humidity_high = None;
+    let mut humidity_high_margin = DEFAULT_ALERT_MARGIN;
+    let mut humidity_low = None;
+    let mut humidity_low_margin = DEFAULT_ALERT_MARGIN;
+    let mut alert_webhook = None;
+    let mut queue_capacity = DEFAULT_QUEUE_CAPACITY;
+    let mut queue_path = None;
+
+    let mut i = 1;
+    while i < argv.len() {
+        match argv[i].as_str() {
+            "--config" => {
+                i += 1;
+                config_path = Some(argv.get(i).unwrap_or_else(|| usage(&argv[0])).clone());
+            }
+            "--i2c-path" => {
+                i += 1;
+                i2c_path = Some(argv.get(i).unwrap_or_else(|| usage(&argv[0])).clone());
+            }
+            "--endpoint" => {
+                i += 1;
+                endpoint = Some(argv.get(i).unwrap_or_else(|| usage(&argv[0])).clone());
+            }
+            "--location" => {
+                i += 1;
+                location = Some(argv.get(i).unwrap_or_else(|| usage(&argv[0])).clone());
+            }
+            "--device-id" => {
+                i += 1;
+                device_id = Some(argv.get(i).unwrap_or_else(|| usage(&argv[0])).clone());
+            }
+            "--auth-token" => {
+                i += 1;
+                auth_token = Some(argv.get(i).unwrap_or_else(|| usage(&argv[0])).clone());
+            }
+            "--sample-interval-ms" => {
+                i += 1;
+                let value = argv.get(i).unwrap_or_else(|| usage(&argv[0]));
+                sample_interval_ms = Some(value.parse().unwrap_or_else(|_| {
+                    eprintln!("--sample-interval-ms must be a positive integer");
+                    process::exit(1);
+                }));
+            }
+            "--sensor" => {
+                i += 1;
+                let value = argv.get(i).unwrap_or_else(|| usage(&argv[0]));
+                sensor_kind = SensorKind::from_arg(value).unwrap_or_else(|| {
+                    eprintln!("unknown sensor: {value}");
+                    process::exit(1);
+                });
+            }
+            "--transport" => {
+                i += 1;
+                let value = argv.get(i).unwrap_or_else(|| usage(&argv[0]));
+                transport_kind = TransportKind::from_arg(value).unwrap_or_else(|| {
+                    eprintln!("unknown transport: {value}");
+                    process::exit(1);
+                });
+            }
+            "--mqtt-broker" => {
+                i += 1;
+                mqtt_broker = Some(argv.get(i).unwrap_or_else(|| usage(&argv[0])).clone());
+            }
+            "--mqtt-topic" => {
+                i += 1;
+                mqtt_topic = Some(argv.get(i).unwrap_or_else(|| usage(&argv[0])).clone());
+            }
+            "--ca-cert" => {
+                i += 1;
+                ca_cert_path = Some(argv.get(i).unwrap_or_else(|| usage(&argv[0])).clone());
+            }
+            "--device-cert" => {
+                i += 1;
+                device_cert_path = Some(argv.get(i).unwrap_or_else(|| usage(&argv[0])).clone());
+            }
+            "--private-key" => {
+                i += 1;
+                private_key_path = Some(argv.get(i).unwrap_or_else(|| usage(&argv[0])).clone());
+            }
+            "--sink" => {
+                i += 1;
+                let value = argv.get(i).unwrap_or_else(|| usage(&argv[0]));
+                sink_kind = SinkKind::from_arg(value).unwrap_or_else(|| {
+                    eprintln!("unknown sink: {value}");
+                    process::exit(1);
+                });
+            }
+            "--influx-url" => {
+                i += 1;
+                influx_url = Some(argv.get(i).unwrap_or_else(|| usage(&argv[0])).clone());
+            }
+            "--influx-db" => {
+                i += 1;
+                influx_db = Some(argv.get(i).unwrap_or_else(|| usage(&argv[0])).clone());
+            }
+            "--influx-user" => {
+                i += 1;
+                influx_user = Some(argv.get(i).unwrap_or_else(|| usage(&argv[0])).clone());
+            }
+            "--influx-pass" => {
+                i += 1;
+                influx_pass = Some(argv.get(i).unwrap_or_else(|| usage(&argv[0])).clone());
+            }
+            "--influx-batch-size" => {
+                i += 1;
+                let value = argv.get(i).unwrap_or_else(|| usage(&argv[0]));
+                influx_batch_size = value.parse::<usize>().ok().filter(|&n| n > 0).unwrap_or_else(|| {
+                    eprintln!("--influx-batch-size must be a positive integer");
+                    process::exit(1);
+                });
+            }
+            "--temp-high" => {
+                i += 1;
+                temp_high = Some(parse_f32_arg(&argv, i, &argv[0]));
+            }
+            "--temp-high-margin" => {
+                i += 1;
+                temp_high_margin = parse_f32_arg(&argv, i, &argv[0]);
+            }
+            "--temp-low" => {
+                i += 1;
+                temp_low = Some(parse_f32_arg(&argv, i, &argv[0]));
+            }
+            "--temp-low-margin" => {
+                i += 1;
+                temp_low_margin = parse_f32_arg(&argv, i, &argv[0]);
+            }
+            "--humidity-high" => {
+                i += 1;
+                humidity_high = Some(parse_f32_arg(&argv, i, &argv[0]));
+            }
+            "--humidity-high-margin" => {
+                i += 1;
+                humidity_high_margin = parse_f32_arg(&argv, i, &argv[0]);
+            }
+            "--humidity-low" => {
+                i += 1;
+                humidity_low = Some(parse_f32_arg(&argv, i, &argv[0]));
+            }
+            "--humidity-low-margin" => {
+                i += 1;
+                humidity_low_margin = parse_f32_arg(&argv, i, &argv[0]);
+            }
+            "--alert-webhook" => {
+                i += 1;
+                alert_webhook = Some(argv.get(i).unwrap_or_else(|| usage(&argv[0])).clone());
+            }
+            "--queue-capacity" => {
+                i += 1;
+                let value = argv.get(i).unwrap_or_else(|| usage(&argv[0]));
+                queue_capacity = value.parse::<usize>().ok().filter(|&n| n > 0).unwrap_or_else(|| {
+                    eprintln!("--queue-capacity must be a positive integer");
+                    process::exit(1);
+                });
+            }
+            "--queue-path" => {
+                i += 1;
+                queue_path = Some(argv.get(i).unwrap_or_else(|| usage(&argv[0])).clone());
+            }
+            flag if flag.starts_with('-') => {
+                eprintln!("unknown flag: {flag}");
+                usage(&argv[0]);
+            }
+            path => {
+                if i2c_path.is_some() {
+                    eprintln!("unexpected extra argument: {path}");
+                    usage(&argv[0]);
+                }
+                i2c_path = Some(path.to_string());
+            }
+        }
+        i += 1;
+    }
+
+    Args {
+        config_path,
+        i2c_path,
+        endpoint,
+        location,
+        device_id,
+        auth_token,
+        sample_interval_ms,
+        sensor_kind,
+        transport_kind,
+        mqtt_broker,
+        mqtt_topic,
+        ca_cert_path,
+        device_cert_path,
+        private_key_path,
+        sink_kind,
+        influx_url,
+        influx_db,
+        influx_user,
+        influx_pass,
+        influx_batch_size,
+        temp_high,
+        temp_high_margin,
+        temp_low,
+        temp_low_margin,
+        humidity_high,
+        humidity_high_margin,
+        humidity_low,
+        humidity_low_margin,
+        alert_webhook,
+        queue_capacity,
+        queue_path,
     }
+}
 
-    let i2c = hal::I2cdev::new(&args[1]).unwrap();
+fn parse_f32_arg(argv: &[String], i: usize, program: &str) -> f32 {
+    argv.get(i)
+        .unwrap_or_else(|| usage(program))
+        .parse()
+        .unwrap_or_else(|_| {
+            eprintln!("expected a number, got {:?}", argv.get(i));
+            process::exit(1);
+        })
+}
 
-    let mut dev = Aht20::new(i2c, hal::Delay).unwrap();
+fn usage(program: &str) -> ! {
+    println!(
+        "usage: {program} [--config PATH] [--i2c-path PATH] [--endpoint URL] \
+         [--location ID] [--device-id ID] [--auth-token TOKEN] [--sample-interval-ms N] \
+         [--sensor aht20|bme280|hdc1080|sht3x|si70xx] \
+         [--transport http|mqtt] [--mqtt-broker HOST] [--mqtt-topic TOPIC] \
+         [--ca-cert PATH] [--device-cert PATH] [--private-key PATH] \
+         [--sink none|influx] [--influx-url URL] [--influx-db NAME] \
+         [--influx-user USER] [--influx-pass PASS] [--influx-batch-size N] \
+         [--temp-high N] [--temp-high-margin N] [--temp-low N] [--temp-low-margin N] \
+         [--humidity-high N] [--humidity-high-margin N] [--humidity-low N] \
+         [--humidity-low-margin N] [--alert-webhook URL] \
+         [--queue-capacity N] [--queue-path PATH] [/dev/i2c-N]"
+    );
+    process::exit(1);
+}
+
+fn build_transport(
+    program: &str,
+    args: &Args,
+    endpoint: &str,
+    auth_token: &str,
+    location: &str,
+    sensor_id: &str,
+) -> Box<dyn Transport> {
+    match args.transport_kind {
+        TransportKind::Http => Box::new(HttpTransport::new(endpoint.into(), auth_token.into())),
+        TransportKind::Mqtt => {
+            let require = |field: &Option<String>, flag: &str| {
+                field.clone().unwrap_or_else(|| {
+                    eprintln!("--transport mqtt requires {flag}");
+                    usage(program)
+                })
+            };
+            let topic = args
+                .mqtt_topic
+                .clone()
+                .unwrap_or_else(|| format!("sensors/{location}/{sensor_id}"));
+            let config = MqttConfig {
+                broker: require(&args.mqtt_broker, "--mqtt-broker"),
+                port: 8883,
+                client_id: sensor_id.into(),
+                topic,
+                ca_cert_path: require(&args.ca_cert_path, "--ca-cert"),
+                device_cert_path: require(&args.device_cert_path, "--device-cert"),
+                private_key_path: require(&args.private_key_path, "--private-key"),
+            };
+            Box::new(MqttTransport::new(config).unwrap_or_else(|e| {
+                eprintln!("failed to connect to MQTT broker: {e}");
+                process::exit(1);
+            }))
+        }
+    }
+}
+
+fn build_sink(program: &str, args: &Args) -> Option<Box<dyn Sink>> {
+    match args.sink_kind {
+        SinkKind::None => None,
+        SinkKind::Influx => {
+            let require = |field: &Option<String>, flag: &str| {
+                field.clone().unwrap_or_else(|| {
+                    eprintln!("--sink influx requires {flag}");
+                    usage(program)
+                })
+            };
+            let config = InfluxConfig {
+                url: require(&args.influx_url, "--influx-url"),
+                database: require(&args.influx_db, "--influx-db"),
+                username: args.influx_user.clone(),
+                password: args.influx_pass.clone(),
+                measurement: "climate".into(),
+                batch_size: args.influx_batch_size,
+            };
+            Some(Box::new(InfluxSink::new(config)))
+        }
+    }
+}
+
+fn build_alerts(args: &Args) -> Alerts {
+    let threshold = |limit: Option<f32>, margin: f32| limit.map(|limit| Threshold { limit, margin });
+
+    Alerts::new(AlertConfig {
+        high_temperature: threshold(args.temp_high, args.temp_high_margin),
+        low_temperature: threshold(args.temp_low, args.temp_low_margin),
+        high_humidity: threshold(args.humidity_high, args.humidity_high_margin),
+        low_humidity: threshold(args.humidity_low, args.humidity_low_margin),
+        webhook_url: args.alert_webhook.clone(),
+    })
+}
+
+fn build_resilient_sender(args: &Args, transport: Box<dyn Transport>) -> ResilientSender {
+    ResilientSender::new(
+        transport,
+        ResilientConfig {
+            capacity: args.queue_capacity,
+            initial_backoff: DEFAULT_INITIAL_BACKOFF,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+            disk_path: args.queue_path.as_ref().map(Into::into),
+        },
+    )
+}
+
+fn load_config(args: &Args) -> config::Config {
+    let cli = ConfigLayer {
+        endpoint: args.endpoint.clone(),
+        location: args.location.clone(),
+        device_id: args.device_id.clone(),
+        auth_token: args.auth_token.clone(),
+        sample_interval_ms: args.sample_interval_ms,
+        i2c_path: args.i2c_path.clone(),
+    };
+
+    // Only an explicit `--config` is passed through; `config::load` falls
+    // back to its own default path when this is `None`, treating that
+    // path's absence as expected rather than an error.
+    let config_path = args.config_path.as_deref().map(Path::new);
+
+    config::load(config_path, cli).unwrap_or_else(|e| {
+        eprintln!("invalid configuration: {e}");
+        process::exit(1);
+    })
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = parse_args();
+    let cfg = load_config(&args);
+
+    let i2c = hal::I2cdev::new(&cfg.i2c_path).unwrap();
+
+    let mut dev = sensor::open(args.sensor_kind, i2c).unwrap_or_else(|e| {
+        eprintln!("failed to initialize sensor: {e}");
+        process::exit(1);
+    });
+    let program = env::args().next().unwrap();
+    let transport = build_transport(
+        &program,
+        &args,
+        &cfg.endpoint,
+        &cfg.auth_token,
+        &cfg.location,
+        &cfg.device_id,
+    );
+    let mut sender = build_resilient_sender(&args, transport);
+    let mut sink = build_sink(&program, &args);
+    let mut alerts = build_alerts(&args);
+    // `config::load` validates that this fits in a u16 before returning.
+    let sample_interval_ms = cfg.sample_interval.as_millis() as u16;
 
     loop {
         let epoch_time: i64 = Utc::now().timestamp();
 
-        let (h, t) = dev.read().unwrap();
+        let measurement = match dev.read_measurement() {
+            Ok(measurement) => measurement,
+            Err(e) => {
+                eprintln!("sensor read failed: {e}");
+                hal::Delay.delay_ms(sample_interval_ms);
+                continue;
+            }
+        };
 
         println!(
             "relative humidity={0}%; temperature={1}C",
-            h.rh(),
-            t.celsius()
+            measurement.humidity_pct, measurement.temperature_c
         );
 
         let post_data = PostData {
-            location: location.into(),
-            device: sensor_id.into(),
-            timestamp: epoch_time.into(),
-            temperature: t.celsius().into(),
-            humidity: h.rh().into(),
+            location: cfg.location.clone(),
+            device: cfg.device_id.clone(),
+            timestamp: epoch_time,
+            temperature: measurement.temperature_c,
+            humidity: measurement.humidity_pct,
+            dew_point: measurement.dew_point_c(),
+            absolute_humidity: measurement.absolute_humidity_g_m3(),
         };
 
-        let client = Client::new();
-        let res = client.post(endpoint)
-            .json(&post_data)
-            .header("authorization-token", "allow")
-            .send()
-            .unwrap();
-
-        if res.status().is_success() {
-            println!("success!");
-        } else if res.status().is_server_error() {
-            println!("server error! Status: {:?}", res.status());
-        } else {
-            println!("Something else happened. Status: {:?}", res.status());
+        alerts.check(&cfg.location, &cfg.device_id, &measurement);
+
+        sender.send(post_data);
+
+        if let Some(sink) = sink.as_mut() {
+            if let Err(e) = sink.record(&cfg.location, &cfg.device_id, epoch_time, &measurement) {
+                eprintln!("sink write failed: {e}");
+            }
         }
 
-        hal::Delay.delay_ms(5000u16);
+        hal::Delay.delay_ms(sample_interval_ms);
     }
 }
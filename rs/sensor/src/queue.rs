@@ -0,0 +1,334 @@
+//! Resilient send loop: bounded offline queue with retry and backoff.
+//!
+//! A transient network blip used to panic the whole process (via a bare
+//! `.unwrap()` on every send) and silently drop the in-flight sample. This
+//! wraps any [`Transport`] so a failed send is queued instead: readings pile
+//! up in a bounded ring buffer (optionally spilling to disk) while the
+//! endpoint is unreachable, and retries resume with exponential backoff plus
+//! jitter, draining the queue oldest-first once the connection comes back.
+//!
+//! When `disk_path` is set, the on-disk copy always mirrors the in-memory
+//! queue: it's rewritten every time the queue changes, so it never grows
+//! past `capacity` and never holds a reading that's already been delivered.
+//! `ResilientSender::new` reads it back in, so readings queued before a
+//! restart aren't lost.
+
+use crate::transport::{PostData, Transport};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs;
+use std::io::Write as _;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Capacity and backoff knobs for the offline queue.
+pub struct ResilientConfig {
+    pub capacity: usize,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub disk_path: Option<PathBuf>,
+}
+
+/// The on-disk (and wire-independent) representation of a queued reading,
+/// one JSON object per line, oldest first.
+#[derive(Serialize, Deserialize)]
+struct QueuedReading {
+    location: String,
+    device: String,
+    timestamp: i64,
+    temperature: f32,
+    humidity: f32,
+    dew_point: Option<f32>,
+    absolute_humidity: Option<f32>,
+}
+
+impl From<&PostData> for QueuedReading {
+    fn from(data: &PostData) -> Self {
+        Self {
+            location: data.location.clone(),
+            device: data.device.clone(),
+            timestamp: data.timestamp,
+            temperature: data.temperature,
+            humidity: data.humidity,
+            dew_point: data.dew_point,
+            absolute_humidity: data.absolute_humidity,
+        }
+    }
+}
+
+impl From<QueuedReading> for PostData {
+    fn from(reading: QueuedReading) -> Self {
+        Self {
+            location: reading.location,
+            device: reading.device,
+            timestamp: reading.timestamp,
+            temperature: reading.temperature,
+            humidity: reading.humidity,
+            dew_point: reading.dew_point,
+            absolute_humidity: reading.absolute_humidity,
+        }
+    }
+}
+
+/// Wraps a [`Transport`], queueing readings across outages instead of
+/// dropping them or panicking the process.
+pub struct ResilientSender {
+    transport: Box<dyn Transport>,
+    config: ResilientConfig,
+    queue: VecDeque<PostData>,
+    backoff: Duration,
+    next_attempt_at: Option<Instant>,
+}
+
+impl ResilientSender {
+    /// Builds a sender, restoring any readings left queued in `disk_path`
+    /// from before a restart.
+    pub fn new(transport: Box<dyn Transport>, config: ResilientConfig) -> Self {
+        let backoff = config.initial_backoff;
+        let queue = config
+            .disk_path
+            .as_ref()
+            .map(|path| load_from_disk(path, config.capacity))
+            .unwrap_or_default();
+
+        Self {
+            transport,
+            config,
+            queue,
+            backoff,
+            next_attempt_at: None,
+        }
+    }
+
+    /// Enqueues `data`, then drains the queue oldest-first while sends keep
+    /// succeeding. Never panics: a send failure just leaves that sample (and
+    /// anything queued behind it) for the next call, after scheduling a
+    /// backoff.
+    pub fn send(&mut self, data: PostData) {
+        self.enqueue(data);
+
+        if let Some(at) = self.next_attempt_at {
+            if Instant::now() < at {
+                return;
+            }
+        }
+
+        while let Some(front) = self.queue.front() {
+            match self.transport.send(front) {
+                Ok(()) => {
+                    self.queue.pop_front();
+                    self.backoff = self.config.initial_backoff;
+                    self.next_attempt_at = None;
+                    self.persist();
+                }
+                Err(e) => {
+                    eprintln!("send failed, queuing for retry: {e}");
+                    self.schedule_retry();
+                    break;
+                }
+            }
+        }
+    }
+
+    fn enqueue(&mut self, data: PostData) {
+        if self.queue.len() >= self.config.capacity {
+            self.queue.pop_front();
+            eprintln!(
+                "offline queue full ({} samples), dropping oldest",
+                self.config.capacity
+            );
+        }
+
+        self.queue.push_back(data);
+        self.persist();
+    }
+
+    fn schedule_retry(&mut self) {
+        self.next_attempt_at = Some(Instant::now() + self.backoff + jitter(self.backoff));
+        self.backoff = (self.backoff * 2).min(self.config.max_backoff);
+    }
+
+    /// Rewrites `disk_path` to match the current in-memory queue, so the
+    /// file never holds more than `capacity` readings and never holds one
+    /// that's already been delivered.
+    fn persist(&self) {
+        let Some(path) = &self.config.disk_path else {
+            return;
+        };
+
+        let body = self
+            .queue
+            .iter()
+            .map(QueuedReading::from)
+            .map(|reading| serde_json::to_string(&reading).unwrap_or_default())
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        if let Err(e) = fs::write(path, body) {
+            eprintln!("failed to persist offline queue to disk: {e}");
+        }
+    }
+}
+
+/// A little jitter (up to 25% of `base`) so a fleet of devices recovering
+/// from the same outage don't all retry in lockstep.
+fn jitter(base: Duration) -> Duration {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .subsec_nanos();
+    let max_ms = (base.as_millis() as u64 / 4).max(1);
+    Duration::from_millis(u64::from(nanos) % max_ms)
+}
+
+/// Loads readings queued before a restart, oldest first, capped at
+/// `capacity` in case the file predates a smaller configured capacity.
+fn load_from_disk(path: &PathBuf, capacity: usize) -> VecDeque<PostData> {
+    let Ok(contents) = fs::read_to_string(path) else {
+        return VecDeque::new();
+    };
+
+    contents
+        .lines()
+        .filter_map(|line| serde_json::from_str::<QueuedReading>(line).ok())
+        .map(PostData::from)
+        .collect::<VecDeque<_>>()
+        .into_iter()
+        .rev()
+        .take(capacity)
+        .rev()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::TransportError;
+
+    enum FakeOutcome {
+        AlwaysFail,
+        SucceedAfter(usize),
+    }
+
+    /// A [`Transport`] driven by a scripted outcome instead of a real
+    /// network call, so the queue/backoff logic can be exercised
+    /// deterministically.
+    struct FakeTransport {
+        outcome: FakeOutcome,
+        calls: usize,
+    }
+
+    impl FakeTransport {
+        fn new(outcome: FakeOutcome) -> Self {
+            Self { outcome, calls: 0 }
+        }
+    }
+
+    impl Transport for FakeTransport {
+        fn send(&mut self, _data: &PostData) -> Result<(), TransportError> {
+            self.calls += 1;
+            match self.outcome {
+                FakeOutcome::AlwaysFail => Err(TransportError::Http("down".into())),
+                FakeOutcome::SucceedAfter(n) if self.calls > n => Ok(()),
+                FakeOutcome::SucceedAfter(_) => Err(TransportError::Http("down".into())),
+            }
+        }
+    }
+
+    fn sample(timestamp: i64) -> PostData {
+        PostData {
+            location: "loc".into(),
+            device: "dev".into(),
+            timestamp,
+            temperature: 20.0,
+            humidity: 50.0,
+            dew_point: None,
+            absolute_humidity: None,
+        }
+    }
+
+    fn config(capacity: usize) -> ResilientConfig {
+        ResilientConfig {
+            capacity,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(100),
+            disk_path: None,
+        }
+    }
+
+    #[test]
+    fn enqueue_drops_oldest_once_capacity_is_exceeded() {
+        let mut sender = ResilientSender::new(Box::new(FakeTransport::new(FakeOutcome::AlwaysFail)), config(2));
+        sender.enqueue(sample(0));
+        sender.enqueue(sample(1));
+        sender.enqueue(sample(2));
+
+        assert_eq!(sender.queue.len(), 2);
+        assert_eq!(sender.queue[0].timestamp, 1);
+        assert_eq!(sender.queue[1].timestamp, 2);
+    }
+
+    #[test]
+    fn send_drains_the_queue_and_resets_backoff_on_success() {
+        let transport = FakeTransport::new(FakeOutcome::SucceedAfter(0));
+        let mut sender = ResilientSender::new(Box::new(transport), config(10));
+
+        sender.send(sample(1));
+        sender.send(sample(2));
+
+        assert!(sender.queue.is_empty());
+        assert_eq!(sender.backoff, Duration::from_millis(10));
+    }
+
+    #[test]
+    fn schedule_retry_doubles_backoff_up_to_max() {
+        let mut sender = ResilientSender::new(
+            Box::new(FakeTransport::new(FakeOutcome::AlwaysFail)),
+            ResilientConfig {
+                capacity: 10,
+                initial_backoff: Duration::from_millis(10),
+                max_backoff: Duration::from_millis(30),
+                disk_path: None,
+            },
+        );
+
+        sender.schedule_retry();
+        assert_eq!(sender.backoff, Duration::from_millis(20));
+        sender.schedule_retry();
+        assert_eq!(sender.backoff, Duration::from_millis(30));
+        sender.schedule_retry();
+        assert_eq!(sender.backoff, Duration::from_millis(30), "capped at max_backoff");
+        assert!(sender.next_attempt_at.unwrap() > Instant::now());
+    }
+
+    #[test]
+    fn persists_and_reloads_the_queue_from_disk() {
+        let path = std::env::temp_dir().join("thermonitor_test_queue_persist.jsonl");
+        fs::remove_file(&path).ok();
+
+        let disk_config = |disk_path: &PathBuf| ResilientConfig {
+            capacity: 10,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(100),
+            disk_path: Some(disk_path.clone()),
+        };
+
+        let mut sender = ResilientSender::new(
+            Box::new(FakeTransport::new(FakeOutcome::AlwaysFail)),
+            disk_config(&path),
+        );
+        sender.send(sample(1));
+        sender.send(sample(2));
+
+        let reloaded = ResilientSender::new(
+            Box::new(FakeTransport::new(FakeOutcome::AlwaysFail)),
+            disk_config(&path),
+        );
+
+        assert_eq!(reloaded.queue.len(), 2);
+        assert_eq!(reloaded.queue[0].timestamp, 1);
+        assert_eq!(reloaded.queue[1].timestamp, 2);
+
+        fs::remove_file(&path).ok();
+    }
+}
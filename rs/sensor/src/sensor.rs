@@ -0,0 +1,281 @@
+//! Sensor abstraction layer.
+//!
+//! `main` used to hardcode the AHT20 driver end to end. This module wraps
+//! each supported I2C part behind one [`Sensor`] trait so the binary can
+//! select a driver at runtime (via `--sensor`) instead of being recompiled
+//! per board.
+
+use linux_embedded_hal as hal;
+use std::fmt;
+
+/// A single reading, normalized across whichever sensor produced it.
+#[derive(Debug, Clone, Copy)]
+pub struct Measurement {
+    pub temperature_c: f32,
+    pub humidity_pct: f32,
+    pub pressure_pa: Option<f32>,
+}
+
+impl Measurement {
+    /// Dew point in °C via the Magnus approximation, the condensation-risk
+    /// signal downstream consumers actually want. `None` when
+    /// `humidity_pct <= 0`, where `ln(RH / 100)` is undefined.
+    pub fn dew_point_c(&self) -> Option<f32> {
+        const A: f32 = 17.62;
+        const B: f32 = 243.12;
+
+        if self.humidity_pct <= 0.0 {
+            return None;
+        }
+
+        let t = self.temperature_c;
+        let gamma = (self.humidity_pct / 100.0).ln() + (A * t) / (B + t);
+        Some((B * gamma) / (A - gamma))
+    }
+
+    /// Absolute humidity in g/m³.
+    pub fn absolute_humidity_g_m3(&self) -> Option<f32> {
+        if self.humidity_pct <= 0.0 {
+            return None;
+        }
+
+        let t = self.temperature_c;
+        let saturation = 6.112 * ((17.67 * t) / (t + 243.5)).exp();
+        Some((saturation * self.humidity_pct * 2.1674) / (273.15 + t))
+    }
+}
+
+/// Failure initializing or reading an I2C sensor.
+#[derive(Debug)]
+pub enum SensorError {
+    Init(String),
+    Read(String),
+}
+
+impl fmt::Display for SensorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SensorError::Init(msg) => write!(f, "sensor init failed: {msg}"),
+            SensorError::Read(msg) => write!(f, "sensor read failed: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for SensorError {}
+
+/// Common interface implemented by every supported I2C sensor.
+pub trait Sensor {
+    fn read_measurement(&mut self) -> Result<Measurement, SensorError>;
+}
+
+/// Which driver to instantiate, selected via `--sensor`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SensorKind {
+    Aht20,
+    Bme280,
+    Hdc1080,
+    Sht3x,
+    Si70xx,
+}
+
+impl SensorKind {
+    pub fn from_arg(s: &str) -> Option<Self> {
+        match s {
+            "aht20" => Some(SensorKind::Aht20),
+            "bme280" => Some(SensorKind::Bme280),
+            "hdc1080" => Some(SensorKind::Hdc1080),
+            "sht3x" => Some(SensorKind::Sht3x),
+            "si70xx" => Some(SensorKind::Si70xx),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SensorKind::Aht20 => "aht20",
+            SensorKind::Bme280 => "bme280",
+            SensorKind::Hdc1080 => "hdc1080",
+            SensorKind::Sht3x => "sht3x",
+            SensorKind::Si70xx => "si70xx",
+        }
+    }
+}
+
+/// Opens `kind` on `i2c`, running whatever driver-specific init (address
+/// selection, oversampling, ...) that part needs, and returns it behind the
+/// common [`Sensor`] interface.
+pub fn open(kind: SensorKind, i2c: hal::I2cdev) -> Result<Box<dyn Sensor>, SensorError> {
+    match kind {
+        SensorKind::Aht20 => Ok(Box::new(Aht20Sensor::new(i2c)?)),
+        SensorKind::Bme280 => Ok(Box::new(Bme280Sensor::new(i2c)?)),
+        SensorKind::Hdc1080 => Ok(Box::new(Hdc1080Sensor::new(i2c)?)),
+        SensorKind::Sht3x => Ok(Box::new(Sht3xSensor::new(i2c)?)),
+        SensorKind::Si70xx => Ok(Box::new(Si70xxSensor::new(i2c)?)),
+    }
+}
+
+struct Aht20Sensor(aht20::Aht20<hal::I2cdev, hal::Delay>);
+
+impl Aht20Sensor {
+    fn new(i2c: hal::I2cdev) -> Result<Self, SensorError> {
+        aht20::Aht20::new(i2c, hal::Delay)
+            .map(Aht20Sensor)
+            .map_err(|e| SensorError::Init(format!("{e:?}")))
+    }
+}
+
+impl Sensor for Aht20Sensor {
+    fn read_measurement(&mut self) -> Result<Measurement, SensorError> {
+        let (h, t) = self
+            .0
+            .read()
+            .map_err(|e| SensorError::Read(format!("{e:?}")))?;
+        Ok(Measurement {
+            temperature_c: t.celsius(),
+            humidity_pct: h.rh(),
+            pressure_pa: None,
+        })
+    }
+}
+
+struct Bme280Sensor(bme280::BME280<hal::I2cdev, hal::Delay>);
+
+impl Bme280Sensor {
+    fn new(i2c: hal::I2cdev) -> Result<Self, SensorError> {
+        let mut dev = bme280::BME280::new_primary(i2c, hal::Delay);
+        dev.init().map_err(|e| SensorError::Init(format!("{e:?}")))?;
+        Ok(Bme280Sensor(dev))
+    }
+}
+
+impl Sensor for Bme280Sensor {
+    fn read_measurement(&mut self) -> Result<Measurement, SensorError> {
+        let m = self
+            .0
+            .measure()
+            .map_err(|e| SensorError::Read(format!("{e:?}")))?;
+        Ok(Measurement {
+            temperature_c: m.temperature,
+            humidity_pct: m.humidity,
+            pressure_pa: Some(m.pressure),
+        })
+    }
+}
+
+struct Hdc1080Sensor(hdc1080::Hdc1080<hal::I2cdev, hal::Delay>);
+
+impl Hdc1080Sensor {
+    fn new(i2c: hal::I2cdev) -> Result<Self, SensorError> {
+        hdc1080::Hdc1080::new(i2c, hal::Delay)
+            .map(Hdc1080Sensor)
+            .map_err(|e| SensorError::Init(format!("{e:?}")))
+    }
+}
+
+impl Sensor for Hdc1080Sensor {
+    fn read_measurement(&mut self) -> Result<Measurement, SensorError> {
+        let reading = self
+            .0
+            .read()
+            .map_err(|e| SensorError::Read(format!("{e:?}")))?;
+        Ok(Measurement {
+            temperature_c: reading.temperature,
+            humidity_pct: reading.humidity,
+            pressure_pa: None,
+        })
+    }
+}
+
+struct Sht3xSensor(sht3x::Sht3x<hal::I2cdev, hal::Delay>);
+
+impl Sht3xSensor {
+    fn new(i2c: hal::I2cdev) -> Result<Self, SensorError> {
+        Ok(Sht3xSensor(sht3x::Sht3x::new(
+            i2c,
+            sht3x::Address::Low,
+            hal::Delay,
+        )))
+    }
+}
+
+impl Sensor for Sht3xSensor {
+    fn read_measurement(&mut self) -> Result<Measurement, SensorError> {
+        let m = self
+            .0
+            .measure(sht3x::ClockStretch::Disabled, sht3x::Repeatability::High)
+            .map_err(|e| SensorError::Read(format!("{e:?}")))?;
+        Ok(Measurement {
+            temperature_c: m.temperature as f32 / 1000.0,
+            humidity_pct: m.humidity as f32 / 1000.0,
+            pressure_pa: None,
+        })
+    }
+}
+
+struct Si70xxSensor(si7021::Si7021<hal::I2cdev, hal::Delay>);
+
+impl Si70xxSensor {
+    fn new(i2c: hal::I2cdev) -> Result<Self, SensorError> {
+        Ok(Si70xxSensor(si7021::Si7021::new(i2c, hal::Delay)))
+    }
+}
+
+impl Sensor for Si70xxSensor {
+    fn read_measurement(&mut self) -> Result<Measurement, SensorError> {
+        let temperature_c = self
+            .0
+            .temperature_celsius()
+            .map_err(|e| SensorError::Read(format!("{e:?}")))?;
+        let humidity_pct = self
+            .0
+            .relative_humidity()
+            .map_err(|e| SensorError::Read(format!("{e:?}")))?;
+        Ok(Measurement {
+            temperature_c,
+            humidity_pct,
+            pressure_pa: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reading(temperature_c: f32, humidity_pct: f32) -> Measurement {
+        Measurement {
+            temperature_c,
+            humidity_pct,
+            pressure_pa: None,
+        }
+    }
+
+    #[test]
+    fn dew_point_matches_known_value_at_20c_50pct() {
+        // Standard reference point for the Magnus approximation.
+        let dew_point = reading(20.0, 50.0).dew_point_c().unwrap();
+        assert!((dew_point - 9.3).abs() < 0.2, "got {dew_point}");
+    }
+
+    #[test]
+    fn dew_point_equals_temperature_at_saturation() {
+        let dew_point = reading(20.0, 100.0).dew_point_c().unwrap();
+        assert!((dew_point - 20.0).abs() < 0.1, "got {dew_point}");
+    }
+
+    #[test]
+    fn dew_point_is_none_at_zero_humidity() {
+        assert_eq!(reading(20.0, 0.0).dew_point_c(), None);
+    }
+
+    #[test]
+    fn absolute_humidity_matches_known_value_at_20c_50pct() {
+        let ah = reading(20.0, 50.0).absolute_humidity_g_m3().unwrap();
+        assert!((ah - 8.65).abs() < 0.2, "got {ah}");
+    }
+
+    #[test]
+    fn absolute_humidity_is_none_at_zero_humidity() {
+        assert_eq!(reading(20.0, 0.0).absolute_humidity_g_m3(), None);
+    }
+}
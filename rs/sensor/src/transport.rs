@@ -0,0 +1,189 @@
+//! Transports for publishing a reading off-device.
+//!
+//! The loop used to be hardwired to a blocking HTTPS POST against a single
+//! API Gateway endpoint. This module abstracts "how a `PostData` payload
+//! leaves the device" behind one [`Transport`] trait, so the same reading
+//! loop can POST over HTTP or publish to an MQTT broker (e.g. AWS IoT Core)
+//! depending on `--transport`.
+
+use reqwest::blocking::Client;
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use std::fmt;
+
+/// The payload pushed out on every iteration of the reading loop.
+pub struct PostData {
+    pub location: String,
+    pub device: String,
+    pub timestamp: i64,
+    pub temperature: f32,
+    pub humidity: f32,
+    /// Dew point in °C, via the Magnus approximation. `None` when the
+    /// humidity reading made the approximation undefined.
+    pub dew_point: Option<f32>,
+    /// Absolute humidity in g/m³.
+    pub absolute_humidity: Option<f32>,
+}
+
+impl Serialize for PostData {
+    /// Defines how a PostData object is serialized for transmission
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut rgb = serializer.serialize_struct("PostData", 7)?;
+        rgb.serialize_field("LocationId", &self.location)?;
+        rgb.serialize_field("DeviceId", &self.device)?;
+        rgb.serialize_field("EpochTime", &self.timestamp)?;
+        rgb.serialize_field("Temperature", &self.temperature)?;
+        rgb.serialize_field("Humidity", &self.humidity)?;
+        rgb.serialize_field("DewPoint", &self.dew_point)?;
+        rgb.serialize_field("AbsoluteHumidity", &self.absolute_humidity)?;
+        rgb.end()
+    }
+}
+
+/// Failure publishing a reading, regardless of which transport was used.
+#[derive(Debug)]
+pub enum TransportError {
+    Http(String),
+    Mqtt(String),
+}
+
+impl fmt::Display for TransportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransportError::Http(msg) => write!(f, "http transport error: {msg}"),
+            TransportError::Mqtt(msg) => write!(f, "mqtt transport error: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for TransportError {}
+
+/// Common interface implemented by every way of getting a reading off-device.
+pub trait Transport {
+    fn send(&mut self, data: &PostData) -> Result<(), TransportError>;
+}
+
+/// Which transport to use, selected via `--transport`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransportKind {
+    Http,
+    Mqtt,
+}
+
+impl TransportKind {
+    pub fn from_arg(s: &str) -> Option<Self> {
+        match s {
+            "http" => Some(TransportKind::Http),
+            "mqtt" => Some(TransportKind::Mqtt),
+            _ => None,
+        }
+    }
+}
+
+/// Blocking HTTPS POST to an API Gateway-style endpoint, authenticated with
+/// a static bearer-style token header.
+pub struct HttpTransport {
+    client: Client,
+    endpoint: String,
+    auth_token: String,
+}
+
+impl HttpTransport {
+    pub fn new(endpoint: String, auth_token: String) -> Self {
+        Self {
+            client: Client::new(),
+            endpoint,
+            auth_token,
+        }
+    }
+}
+
+impl Transport for HttpTransport {
+    fn send(&mut self, data: &PostData) -> Result<(), TransportError> {
+        let res = self
+            .client
+            .post(&self.endpoint)
+            .json(data)
+            .header("authorization-token", &self.auth_token)
+            .send()
+            .map_err(|e| TransportError::Http(e.to_string()))?;
+
+        if res.status().is_success() {
+            println!("success!");
+        } else if res.status().is_server_error() {
+            println!("server error! Status: {:?}", res.status());
+        } else {
+            println!("Something else happened. Status: {:?}", res.status());
+        }
+        Ok(())
+    }
+}
+
+/// mTLS client-certificate material and connection details for an MQTT
+/// broker such as an AWS IoT Core endpoint.
+pub struct MqttConfig {
+    pub broker: String,
+    pub port: u16,
+    pub client_id: String,
+    pub topic: String,
+    pub ca_cert_path: String,
+    pub device_cert_path: String,
+    pub private_key_path: String,
+}
+
+/// Publishes readings as JSON over a persistent, mutually-TLS-authenticated
+/// MQTT session, instead of one HTTPS request per sample.
+pub struct MqttTransport {
+    client: mqtt::Client,
+    topic: String,
+}
+
+impl MqttTransport {
+    pub fn new(config: MqttConfig) -> Result<Self, TransportError> {
+        let create_opts = mqtt::CreateOptionsBuilder::new()
+            .server_uri(format!("ssl://{}:{}", config.broker, config.port))
+            .client_id(&config.client_id)
+            .finalize();
+
+        let client = mqtt::Client::new(create_opts)
+            .map_err(|e| TransportError::Mqtt(e.to_string()))?;
+
+        let ssl_opts = mqtt::SslOptionsBuilder::new()
+            .trust_store(&config.ca_cert_path)
+            .map_err(|e| TransportError::Mqtt(e.to_string()))?
+            .key_store(&config.device_cert_path)
+            .map_err(|e| TransportError::Mqtt(e.to_string()))?
+            .private_key(&config.private_key_path)
+            .map_err(|e| TransportError::Mqtt(e.to_string()))?
+            .finalize();
+
+        let conn_opts = mqtt::ConnectOptionsBuilder::new()
+            .ssl_options(ssl_opts)
+            .clean_session(false)
+            .finalize();
+
+        client
+            .connect(conn_opts)
+            .map_err(|e| TransportError::Mqtt(e.to_string()))?;
+
+        Ok(Self {
+            client,
+            topic: config.topic,
+        })
+    }
+}
+
+impl Transport for MqttTransport {
+    fn send(&mut self, data: &PostData) -> Result<(), TransportError> {
+        let payload =
+            serde_json::to_vec(data).map_err(|e| TransportError::Mqtt(e.to_string()))?;
+        let msg = mqtt::Message::new(&self.topic, payload, mqtt::QOS_1);
+        self.client
+            .publish(msg)
+            .map_err(|e| TransportError::Mqtt(e.to_string()))?;
+        println!("published to {}", self.topic);
+        Ok(())
+    }
+}